@@ -1,11 +1,44 @@
 use std::collections::{HashMap, HashSet};
 use std::io::{Cursor, Error, ErrorKind, Read};
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub enum ArchiveFileKind {
     Tar,
     TarGz,
+    #[cfg(feature = "zstd")]
+    TarZstd,
+    #[cfg(feature = "xz")]
+    TarXz,
+    #[cfg(feature = "bzip2")]
+    TarBz2,
+    #[cfg(feature = "lz4")]
+    TarLz4,
+}
+
+/// Sniffs an [`ArchiveFileKind`] from the leading bytes of an archive, for cases where the
+/// file extension is missing or unrecognized.
+pub(crate) fn detect_kind_from_bytes(bytes: &[u8]) -> Option<ArchiveFileKind> {
+    if bytes.starts_with(&[0x1f, 0x8b]) {
+        return Some(ArchiveFileKind::TarGz);
+    }
+    #[cfg(feature = "zstd")]
+    if bytes.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+        return Some(ArchiveFileKind::TarZstd);
+    }
+    #[cfg(feature = "xz")]
+    if bytes.starts_with(&[0xfd, 0x37, 0x7a, 0x58, 0x5a]) {
+        return Some(ArchiveFileKind::TarXz);
+    }
+    #[cfg(feature = "bzip2")]
+    if bytes.starts_with(&[0x42, 0x5a, 0x68]) {
+        return Some(ArchiveFileKind::TarBz2);
+    }
+    if bytes.len() >= 262 && &bytes[257..262] == b"ustar" {
+        return Some(ArchiveFileKind::Tar);
+    }
+    None
 }
 
 #[derive(Clone, Debug)]
@@ -17,6 +50,23 @@ impl Default for ArchiveFileExtensionList {
         list.insert(".tar".into(), ArchiveFileKind::Tar);
         list.insert(".tar.gz".into(), ArchiveFileKind::TarGz);
         list.insert(".tgz".into(), ArchiveFileKind::TarGz);
+        #[cfg(feature = "zstd")]
+        {
+            list.insert(".tar.zst".into(), ArchiveFileKind::TarZstd);
+            list.insert(".tzst".into(), ArchiveFileKind::TarZstd);
+        }
+        #[cfg(feature = "xz")]
+        {
+            list.insert(".tar.xz".into(), ArchiveFileKind::TarXz);
+            list.insert(".txz".into(), ArchiveFileKind::TarXz);
+        }
+        #[cfg(feature = "bzip2")]
+        {
+            list.insert(".tar.bz2".into(), ArchiveFileKind::TarBz2);
+            list.insert(".tbz2".into(), ArchiveFileKind::TarBz2);
+        }
+        #[cfg(feature = "lz4")]
+        list.insert(".tar.lz4".into(), ArchiveFileKind::TarLz4);
         Self(list)
     }
 }
@@ -37,38 +87,193 @@ impl ArchiveFileExtensionList {
     }
 }
 
+/// Location of a regular file's payload within one of `Archive`'s raw, uncompressed buffers.
+#[derive(Debug)]
+struct IndexedEntry {
+    raw: usize,
+    offset: usize,
+    size: usize,
+}
+
+fn normalize_tar_path(path: std::borrow::Cow<'_, Path>) -> PathBuf {
+    if let Ok(path) = path.strip_prefix("./") {
+        path.to_owned()
+    } else {
+        path.into_owned()
+    }
+}
+
+/// Resolves a symlink's target relative to the symlink's own parent directory, collapsing `.`
+/// and `..` components rather than keeping them literally.
+fn resolve_symlink_target(link_path: &Path, target: &Path) -> PathBuf {
+    let mut components: Vec<std::ffi::OsString> = link_path
+        .parent()
+        .into_iter()
+        .flat_map(|parent| parent.components())
+        .map(|c| c.as_os_str().to_owned())
+        .collect();
+
+    for component in target.components() {
+        match component {
+            std::path::Component::ParentDir => {
+                components.pop();
+            }
+            std::path::Component::CurDir => {}
+            std::path::Component::Normal(part) => components.push(part.to_owned()),
+            _ => {}
+        }
+    }
+
+    components.into_iter().collect()
+}
+
+/// Maximum number of link hops followed before `Archive` gives up, to reject symlink cycles.
+const MAX_LINK_HOPS: usize = 16;
+
 #[derive(Debug)]
 pub struct Archive {
-    files: HashMap<PathBuf, Vec<u8>>,
+    /// Eagerly-decoded file contents, used for compressed archives where random access isn't
+    /// free. The payload is an `Arc` handle into `interned` so identical files across layered
+    /// archives share one allocation.
+    files: HashMap<PathBuf, Arc<[u8]>>,
+    /// Content-addressed storage for eagerly-decoded payloads, keyed by a hash of their bytes.
+    interned: HashMap<blake3::Hash, Arc<[u8]>>,
+    /// Offset index into `raw` for files backed by an uncompressed tar input, read on demand.
+    indexed: HashMap<PathBuf, IndexedEntry>,
+    /// Raw bytes of every uncompressed tar appended so far, kept alive for `indexed` to slice
+    /// into.
+    raw: Vec<Arc<[u8]>>,
     dirs: HashSet<PathBuf>,
+    /// Link name -> target for `Symlink`/`Link` tar entries, resolved on lookup.
+    links: HashMap<PathBuf, PathBuf>,
 }
 
 impl Archive {
     pub fn new() -> Self {
         Self {
             files: HashMap::default(),
+            interned: HashMap::default(),
+            indexed: HashMap::default(),
+            raw: Vec::new(),
             dirs: HashSet::default(),
+            links: HashMap::default(),
+        }
+    }
+
+    /// Interns `data` in `interned` keyed by its content hash, returning a shared handle so
+    /// byte-identical files across archives reuse the same allocation.
+    fn intern(&mut self, data: Vec<u8>) -> Arc<[u8]> {
+        let hash = blake3::hash(&data);
+        self.interned
+            .entry(hash)
+            .or_insert_with(|| Arc::from(data.into_boxed_slice()))
+            .clone()
+    }
+
+    /// Follows `links` from `path` until a non-link path is reached, guarding against cycles.
+    fn resolve(&self, path: &Path) -> Result<PathBuf, Error> {
+        let mut current = path.to_owned();
+        for _ in 0..MAX_LINK_HOPS {
+            match self.links.get(&current) {
+                Some(target) => current = target.clone(),
+                None => return Ok(current),
+            }
         }
+        Err(Error::other(format!(
+            "too many symlink hops resolving \"{}\"",
+            path.display()
+        )))
     }
 
     pub fn append(&mut self, kind: ArchiveFileKind, input: Vec<u8>) -> Result<(), Error> {
         match kind {
-            ArchiveFileKind::Tar => self.read_tar(input),
+            ArchiveFileKind::Tar => self.read_tar_indexed(input),
             ArchiveFileKind::TarGz => self.read_tar_gz(input),
+            #[cfg(feature = "zstd")]
+            ArchiveFileKind::TarZstd => self.read_tar_zstd(input),
+            #[cfg(feature = "xz")]
+            ArchiveFileKind::TarXz => self.read_tar_xz(input),
+            #[cfg(feature = "bzip2")]
+            ArchiveFileKind::TarBz2 => self.read_tar_bz2(input),
+            #[cfg(feature = "lz4")]
+            ArchiveFileKind::TarLz4 => self.read_tar_lz4(input),
         }
     }
 
+    /// Builds an offset index over an uncompressed tar input instead of buffering every entry's
+    /// contents, so `read_file` can slice into the retained raw bytes on demand.
+    fn read_tar_indexed(&mut self, input: Vec<u8>) -> Result<(), Error> {
+        let raw_index = self.raw.len();
+        let mut tar = tar::Archive::new(Cursor::new(&input[..]));
+
+        for entry in tar.entries()? {
+            let mut entry = entry?;
+            let path = normalize_tar_path(entry.path()?);
+            if path.as_os_str().is_empty() {
+                continue;
+            }
+
+            match entry.header().entry_type() {
+                tar::EntryType::Regular => {
+                    let offset = entry.raw_file_position() as usize;
+                    // `entry.size()` accounts for PAX/GNU extended-size overrides, unlike
+                    // `entry.header().size()` which only reads the raw header field.
+                    let size = entry.size() as usize;
+                    let overwritten = self
+                        .indexed
+                        .insert(
+                            path.clone(),
+                            IndexedEntry {
+                                raw: raw_index,
+                                offset,
+                                size,
+                            },
+                        )
+                        .is_some()
+                        || self.files.remove(&path).is_some()
+                        || self.links.remove(&path).is_some();
+                    if overwritten {
+                        log::info!("overwrite \"{}\"", path.display());
+                    }
+                }
+                tar::EntryType::Directory => {
+                    self.dirs.insert(path);
+                }
+                tar::EntryType::Symlink => {
+                    if let Some(target) = entry.link_name()? {
+                        self.files.remove(&path);
+                        self.indexed.remove(&path);
+                        self.links
+                            .insert(path.clone(), resolve_symlink_target(&path, &target));
+                    }
+                }
+                tar::EntryType::Link => {
+                    if let Some(target) = entry.link_name()? {
+                        self.files.remove(&path);
+                        self.indexed.remove(&path);
+                        self.links.insert(path, normalize_tar_path(target));
+                    }
+                }
+                t => {
+                    log::warn!(
+                        "skipping unsupported tar entry type {:?} at \"{}\"",
+                        t,
+                        path.display()
+                    );
+                }
+            }
+        }
+
+        self.raw.push(Arc::from(input.into_boxed_slice()));
+        Ok(())
+    }
+
     fn read_tar(&mut self, input: Vec<u8>) -> Result<(), Error> {
         let mut tar = tar::Archive::new(Cursor::new(input));
 
         for entry in tar.entries()? {
             let mut entry = entry?;
-            let path = entry.path()?;
-            let path = if let Ok(path) = path.strip_prefix("./") {
-                path.to_owned()
-            } else {
-                path.into_owned()
-            };
+            let path = normalize_tar_path(entry.path()?);
             if path.as_os_str().is_empty() {
                 continue;
             }
@@ -77,18 +282,38 @@ impl Archive {
                 tar::EntryType::Regular => {
                     let mut file = Vec::new();
                     entry.read_to_end(&mut file)?;
-                    if self.files.insert(path, file).is_some() {
-                        log::info!(
-                            "overwrite \"{}\"",
-                            String::from_utf8_lossy(&entry.path_bytes())
-                        );
+                    let data = self.intern(file);
+                    let overwritten = self.files.insert(path.clone(), data).is_some()
+                        || self.indexed.remove(&path).is_some()
+                        || self.links.remove(&path).is_some();
+                    if overwritten {
+                        log::info!("overwrite \"{}\"", path.display());
                     }
                 }
                 tar::EntryType::Directory => {
                     self.dirs.insert(path);
                 }
+                tar::EntryType::Symlink => {
+                    if let Some(target) = entry.link_name()? {
+                        self.files.remove(&path);
+                        self.indexed.remove(&path);
+                        self.links
+                            .insert(path.clone(), resolve_symlink_target(&path, &target));
+                    }
+                }
+                tar::EntryType::Link => {
+                    if let Some(target) = entry.link_name()? {
+                        self.files.remove(&path);
+                        self.indexed.remove(&path);
+                        self.links.insert(path, normalize_tar_path(target));
+                    }
+                }
                 t => {
-                    return Err(Error::other(format!("Unexpected file type in tar {:?}", t)));
+                    log::warn!(
+                        "skipping unsupported tar entry type {:?} at \"{}\"",
+                        t,
+                        path.display()
+                    );
                 }
             }
         }
@@ -97,14 +322,19 @@ impl Archive {
     }
 
     pub fn read_file(&self, path: &Path) -> Result<Vec<u8>, Error> {
-        if let Some(data) = self.files.get(path).cloned() {
-            Ok(data)
+        let path = &self.resolve(path)?;
+        if let Some(entry) = self.indexed.get(path) {
+            let raw = &self.raw[entry.raw];
+            Ok(raw[entry.offset..entry.offset + entry.size].to_vec())
+        } else if let Some(data) = self.files.get(path) {
+            Ok(data.to_vec())
         } else {
             Err(Error::new(ErrorKind::NotFound, path.display().to_string()))
         }
     }
 
     pub fn read_dir(&self, path: &Path) -> Result<Dir, Error> {
+        let path = &self.resolve(path)?;
         if !self.is_dir(path)? {
             return Err(Error::other(format!(
                 "{} is not a directory",
@@ -113,10 +343,17 @@ impl Archive {
         }
 
         let mut files = Vec::new();
+        let mut seen = HashSet::new();
 
-        for p in self.files.keys() {
+        for p in self
+            .files
+            .keys()
+            .chain(self.indexed.keys())
+            .chain(self.links.keys())
+        {
             if let Some(parent) = p.parent()
                 && parent == path
+                && seen.insert(p)
             {
                 files.push(p.to_owned())
             }
@@ -134,9 +371,10 @@ impl Archive {
     }
 
     pub fn is_dir(&self, path: &Path) -> Result<bool, Error> {
+        let path = &self.resolve(path)?;
         if self.dirs.contains(path) {
             Ok(true)
-        } else if self.files.contains_key(path) {
+        } else if self.files.contains_key(path) || self.indexed.contains_key(path) {
             Ok(false)
         } else {
             Err(Error::new(ErrorKind::NotFound, path.display().to_string()))
@@ -149,6 +387,38 @@ impl Archive {
         gz.read_to_end(&mut decoded)?;
         self.read_tar(decoded)
     }
+
+    #[cfg(feature = "zstd")]
+    fn read_tar_zstd(&mut self, input: Vec<u8>) -> Result<(), Error> {
+        let mut decoded = Vec::new();
+        let mut zstd = zstd::stream::read::Decoder::new(Cursor::new(input))?;
+        zstd.read_to_end(&mut decoded)?;
+        self.read_tar(decoded)
+    }
+
+    #[cfg(feature = "xz")]
+    fn read_tar_xz(&mut self, input: Vec<u8>) -> Result<(), Error> {
+        let mut decoded = Vec::new();
+        let mut xz = xz2::read::XzDecoder::new(Cursor::new(input));
+        xz.read_to_end(&mut decoded)?;
+        self.read_tar(decoded)
+    }
+
+    #[cfg(feature = "bzip2")]
+    fn read_tar_bz2(&mut self, input: Vec<u8>) -> Result<(), Error> {
+        let mut decoded = Vec::new();
+        let mut bz = bzip2::read::BzDecoder::new(Cursor::new(input));
+        bz.read_to_end(&mut decoded)?;
+        self.read_tar(decoded)
+    }
+
+    #[cfg(feature = "lz4")]
+    fn read_tar_lz4(&mut self, input: Vec<u8>) -> Result<(), Error> {
+        let mut decoded = Vec::new();
+        let mut lz4 = lz4::Decoder::new(Cursor::new(input))?;
+        lz4.read_to_end(&mut decoded)?;
+        self.read_tar(decoded)
+    }
 }
 
 #[derive(Debug)]
@@ -164,3 +434,194 @@ impl bevy::tasks::futures_lite::Stream for Dir {
         std::task::Poll::Ready(self.get_mut().0.pop())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn build_tar(entries: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(Vec::new());
+        for (path, data) in entries {
+            let mut header = tar::Header::new_gnu();
+            header.set_path(path).unwrap();
+            header.set_size(data.len() as u64);
+            header.set_cksum();
+            builder.append(&header, *data).unwrap();
+        }
+        builder.into_inner().unwrap()
+    }
+
+    fn build_tar_gz(entries: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        gz.write_all(&build_tar(entries)).unwrap();
+        gz.finish().unwrap()
+    }
+
+    #[test]
+    fn indexed_tar_overrides_duplicate_path_with_last_entry() {
+        let tar = build_tar(&[("a.txt", b"first"), ("a.txt", b"second")]);
+        let mut archive = Archive::new();
+        archive.append(ArchiveFileKind::Tar, tar).unwrap();
+
+        assert_eq!(archive.read_file(Path::new("a.txt")).unwrap(), b"second");
+    }
+
+    #[test]
+    fn indexed_tar_reads_out_of_order_entries() {
+        let tar = build_tar(&[("dir/b.txt", b"b"), ("dir/a.txt", b"a")]);
+        let mut archive = Archive::new();
+        archive.append(ArchiveFileKind::Tar, tar).unwrap();
+
+        assert_eq!(archive.read_file(Path::new("dir/a.txt")).unwrap(), b"a");
+        assert_eq!(archive.read_file(Path::new("dir/b.txt")).unwrap(), b"b");
+    }
+
+    #[test]
+    fn identical_files_across_archives_share_one_interned_buffer() {
+        let data: &[u8] = b"shared payload";
+        let first = build_tar_gz(&[("one/shared.bin", data)]);
+        let second = build_tar_gz(&[("two/shared.bin", data)]);
+        let mut archive = Archive::new();
+        archive.append(ArchiveFileKind::TarGz, first).unwrap();
+        archive.append(ArchiveFileKind::TarGz, second).unwrap();
+
+        assert_eq!(archive.interned.len(), 1);
+        let a = archive.files.get(Path::new("one/shared.bin")).unwrap();
+        let b = archive.files.get(Path::new("two/shared.bin")).unwrap();
+        assert!(Arc::ptr_eq(a, b));
+    }
+
+    #[test]
+    fn hardlink_target_is_normalized_to_match_stored_paths() {
+        let mut builder = tar::Builder::new(Vec::new());
+
+        let mut file_header = tar::Header::new_gnu();
+        file_header.set_path("dir/orig.txt").unwrap();
+        file_header.set_size(5);
+        file_header.set_cksum();
+        builder.append(&file_header, &b"hello"[..]).unwrap();
+
+        let mut link_header = tar::Header::new_gnu();
+        link_header.set_path("dir/alias.txt").unwrap();
+        link_header.set_entry_type(tar::EntryType::Link);
+        link_header.set_size(0);
+        link_header.set_link_name("./dir/orig.txt").unwrap();
+        link_header.set_cksum();
+        builder.append(&link_header, &[][..]).unwrap();
+
+        let mut archive = Archive::new();
+        archive
+            .append(ArchiveFileKind::Tar, builder.into_inner().unwrap())
+            .unwrap();
+
+        assert_eq!(
+            archive.read_file(Path::new("dir/alias.txt")).unwrap(),
+            b"hello"
+        );
+    }
+
+    #[test]
+    fn read_dir_lists_symlink_entries() {
+        let mut builder = tar::Builder::new(Vec::new());
+
+        let mut dir_header = tar::Header::new_gnu();
+        dir_header.set_path("assets/").unwrap();
+        dir_header.set_entry_type(tar::EntryType::Directory);
+        dir_header.set_size(0);
+        dir_header.set_cksum();
+        builder.append(&dir_header, &[][..]).unwrap();
+
+        let mut file_header = tar::Header::new_gnu();
+        file_header.set_path("assets/orig.txt").unwrap();
+        file_header.set_size(5);
+        file_header.set_cksum();
+        builder.append(&file_header, &b"hello"[..]).unwrap();
+
+        let mut link_header = tar::Header::new_gnu();
+        link_header.set_path("assets/alias.txt").unwrap();
+        link_header.set_entry_type(tar::EntryType::Symlink);
+        link_header.set_size(0);
+        link_header.set_link_name("orig.txt").unwrap();
+        link_header.set_cksum();
+        builder.append(&link_header, &[][..]).unwrap();
+
+        let mut archive = Archive::new();
+        archive
+            .append(ArchiveFileKind::Tar, builder.into_inner().unwrap())
+            .unwrap();
+
+        let dir = archive.read_dir(Path::new("assets")).unwrap();
+        assert!(dir.0.contains(&PathBuf::from("assets/alias.txt")));
+    }
+
+    #[test]
+    fn regular_file_overrides_previously_linked_path() {
+        let mut first = tar::Builder::new(Vec::new());
+        let mut orig_header = tar::Header::new_gnu();
+        orig_header.set_path("brand/logo.png").unwrap();
+        orig_header.set_size(4);
+        orig_header.set_cksum();
+        first.append(&orig_header, &b"base"[..]).unwrap();
+
+        let mut link_header = tar::Header::new_gnu();
+        link_header.set_path("logo.png").unwrap();
+        link_header.set_entry_type(tar::EntryType::Symlink);
+        link_header.set_size(0);
+        link_header.set_link_name("brand/logo.png").unwrap();
+        link_header.set_cksum();
+        first.append(&link_header, &[][..]).unwrap();
+
+        let mut second = tar::Builder::new(Vec::new());
+        let mut addon_header = tar::Header::new_gnu();
+        addon_header.set_path("logo.png").unwrap();
+        addon_header.set_size(5);
+        addon_header.set_cksum();
+        second.append(&addon_header, &b"addon"[..]).unwrap();
+
+        let mut archive = Archive::new();
+        archive
+            .append(ArchiveFileKind::Tar, first.into_inner().unwrap())
+            .unwrap();
+        archive
+            .append(ArchiveFileKind::Tar, second.into_inner().unwrap())
+            .unwrap();
+
+        assert_eq!(archive.read_file(Path::new("logo.png")).unwrap(), b"addon");
+    }
+
+    #[test]
+    fn link_overrides_previously_regular_path() {
+        let mut first = tar::Builder::new(Vec::new());
+        let mut orig_header = tar::Header::new_gnu();
+        orig_header.set_path("logo.png").unwrap();
+        orig_header.set_size(4);
+        orig_header.set_cksum();
+        first.append(&orig_header, &b"base"[..]).unwrap();
+
+        let mut second = tar::Builder::new(Vec::new());
+        let mut target_header = tar::Header::new_gnu();
+        target_header.set_path("brand/logo.png").unwrap();
+        target_header.set_size(5);
+        target_header.set_cksum();
+        second.append(&target_header, &b"addon"[..]).unwrap();
+
+        let mut link_header = tar::Header::new_gnu();
+        link_header.set_path("logo.png").unwrap();
+        link_header.set_entry_type(tar::EntryType::Symlink);
+        link_header.set_size(0);
+        link_header.set_link_name("brand/logo.png").unwrap();
+        link_header.set_cksum();
+        second.append(&link_header, &[][..]).unwrap();
+
+        let mut archive = Archive::new();
+        archive
+            .append(ArchiveFileKind::Tar, first.into_inner().unwrap())
+            .unwrap();
+        archive
+            .append(ArchiveFileKind::Tar, second.into_inner().unwrap())
+            .unwrap();
+
+        assert_eq!(archive.read_file(Path::new("logo.png")).unwrap(), b"addon");
+    }
+}
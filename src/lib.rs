@@ -23,6 +23,13 @@ pub struct AssetTarPlugin {
     pub archive_files: Vec<PathBuf>,
     pub archive_file_extension_list: ArchiveFileExtensionList,
     pub addon_directories: Vec<PathBuf>,
+    /// When an archive's extension doesn't resolve to a known [`ArchiveFileKind`], fall back to
+    /// sniffing its format from the leading bytes. Useful for addon archives downloaded under
+    /// arbitrary names.
+    pub detect_format_by_content: bool,
+    /// Descend into subdirectories of each `addon_directories` entry, so archives nested in mod
+    /// folders (e.g. `mods/foo/pack.tar.gz`) are picked up too.
+    pub recursive_addons: bool,
 }
 
 impl Default for AssetTarPlugin {
@@ -31,6 +38,8 @@ impl Default for AssetTarPlugin {
             archive_files: vec![PathBuf::from("assets.tar.gz")],
             archive_file_extension_list: ArchiveFileExtensionList::default(),
             addon_directories: Vec::new(),
+            detect_format_by_content: false,
+            recursive_addons: false,
         }
     }
 }
@@ -40,6 +49,8 @@ impl Plugin for AssetTarPlugin {
         let archive_files = self.archive_files.clone();
         let archive_file_extension_list = self.archive_file_extension_list.clone();
         let addon_directories = self.addon_directories.clone();
+        let detect_format_by_content = self.detect_format_by_content;
+        let recursive_addons = self.recursive_addons;
 
         app.register_asset_source(
             AssetSourceId::Default,
@@ -48,6 +59,8 @@ impl Plugin for AssetTarPlugin {
                     archive_files: archive_files.clone(),
                     archive_file_extension_list: archive_file_extension_list.clone(),
                     addon_directories: addon_directories.clone(),
+                    detect_format_by_content,
+                    recursive_addons,
                     reader: AssetSource::get_default_reader("".to_string())(),
                     archive: Mutex::default(),
                 })
@@ -60,6 +73,8 @@ struct TarAssetReader {
     archive_files: Vec<PathBuf>,
     archive_file_extension_list: ArchiveFileExtensionList,
     addon_directories: Vec<PathBuf>,
+    detect_format_by_content: bool,
+    recursive_addons: bool,
     reader: Box<dyn ErasedAssetReader>,
     archive: Mutex<Option<Archive>>,
 }
@@ -74,15 +89,24 @@ impl TarAssetReader {
             let mut loading = Archive::new();
 
             for file in &self.archive_files {
-                let Some(kind) = self.archive_file_extension_list.from_path(file) else {
-                    log::warn!("unknown extension for \"{}\"", file.display());
-                    continue;
-                };
-
                 let mut buf = Vec::new();
                 if let Ok(mut r) = self.reader.read(file).await {
                     r.read_to_end(&mut buf).await?;
                 }
+
+                let kind = self
+                    .archive_file_extension_list
+                    .from_path(file)
+                    .or_else(|| {
+                        self.detect_format_by_content
+                            .then(|| archive::detect_kind_from_bytes(&buf))
+                            .flatten()
+                    });
+                let Some(kind) = kind else {
+                    log::warn!("unknown archive format for \"{}\"", file.display());
+                    continue;
+                };
+
                 if let Err(e) = loading.append(kind, buf) {
                     log::warn!("cannot read \"{}\": {}", file.display(), e);
                 }
@@ -91,6 +115,8 @@ impl TarAssetReader {
                 &mut loading,
                 &self.addon_directories,
                 &self.archive_file_extension_list,
+                self.detect_format_by_content,
+                self.recursive_addons,
             )
             .await;
             *archive = Some(loading);
@@ -153,20 +179,95 @@ fn to_asset_reader_err(e: std::io::Error, path: &Path) -> AssetReaderError {
     }
 }
 
+/// The maximum depth `collect_addon_files` will descend into nested addon directories,
+/// bounding how deep a mod folder's internal structure is walked.
+const MAX_ADDON_DIR_DEPTH: usize = 16;
+
+/// Loads every archive reachable from `dirs` into `loading`.
+///
+/// Each entry in `dirs` is walked and sorted independently, so a later-listed addon
+/// directory always overrides same-named files from an earlier-listed one:
+/// `addon_directories` order is the override precedence, with alphabetical order
+/// only breaking ties within a single directory.
 #[cfg(not(target_arch = "wasm32"))]
 async fn load_from_addon_dirs(
     loading: &mut Archive,
     dirs: &[PathBuf],
     archive_file_extension_list: &ArchiveFileExtensionList,
+    detect_format_by_content: bool,
+    recursive_addons: bool,
 ) {
+    let mut files = Vec::new();
+    for dir in dirs {
+        let mut dir_files = Vec::new();
+        collect_addon_files(
+            dir,
+            recursive_addons,
+            archive_file_extension_list,
+            detect_format_by_content,
+            0,
+            &mut dir_files,
+        )
+        .await;
+        dir_files.sort();
+        files.extend(dir_files);
+    }
+
+    for path in files {
+        let known_kind = archive_file_extension_list.from_path(&path);
+        match async_fs::read(&path).await {
+            Ok(bytes) => {
+                let kind = known_kind.or_else(|| {
+                    detect_format_by_content
+                        .then(|| archive::detect_kind_from_bytes(&bytes))
+                        .flatten()
+                });
+                let Some(kind) = kind else {
+                    log::warn!("unknown archive format for \"{}\"", path.display());
+                    continue;
+                };
+                if let Err(e) = loading.append(kind, bytes) {
+                    log::warn!("cannot read \"{}\": {}", path.display(), e);
+                }
+            }
+            Err(e) => {
+                log::warn!("cannot read \"{}\": {}", path.display(), e);
+            }
+        }
+    }
+}
+
+/// Walks `dir`, collecting every file whose extension is in `archive_file_extension_list` into
+/// `out`; when `detect_format_by_content` is set, every regular file is collected regardless of
+/// its extension, and its format is resolved later when it is loaded. Descends into subdirectories when `recursive` is set, up to `MAX_ADDON_DIR_DEPTH`
+/// levels deep; directory symlinks are never followed, so a symlink cycle under a mod folder
+/// cannot cause unbounded recursion.
+#[cfg(not(target_arch = "wasm32"))]
+fn collect_addon_files<'a>(
+    dir: &'a Path,
+    recursive: bool,
+    archive_file_extension_list: &'a ArchiveFileExtensionList,
+    detect_format_by_content: bool,
+    depth: usize,
+    out: &'a mut Vec<PathBuf>,
+) -> std::pin::Pin<Box<dyn std::future::Future<Output = ()> + 'a>> {
     use bevy::tasks::futures_lite::StreamExt;
 
-    for dir in dirs {
+    Box::pin(async move {
+        if depth >= MAX_ADDON_DIR_DEPTH {
+            log::warn!(
+                "addon directory \"{}\" exceeds max depth of {}, not descending further",
+                dir.display(),
+                MAX_ADDON_DIR_DEPTH
+            );
+            return;
+        }
+
         let mut entries = match async_fs::read_dir(dir).await {
-            Ok(path) => path,
+            Ok(entries) => entries,
             Err(e) => {
                 log::warn!("cannot read directory \"{}\": {}", dir.display(), e);
-                continue;
+                return;
             }
         };
 
@@ -180,24 +281,48 @@ async fn load_from_addon_dirs(
                 }
             };
             let path = entry.path();
-            let Some(kind) = archive_file_extension_list.from_path(&path) else {
-                continue;
-            };
-            match async_fs::read(&path).await {
-                Ok(bytes) => {
-                    if let Err(e) = loading.append(kind, bytes) {
-                        log::warn!("cannot read \"{}\": {}", path.display(), e);
+
+            match async_fs::symlink_metadata(&path).await {
+                Ok(meta) if meta.is_dir() => {
+                    if recursive {
+                        collect_addon_files(
+                            &path,
+                            recursive,
+                            archive_file_extension_list,
+                            detect_format_by_content,
+                            depth + 1,
+                            out,
+                        )
+                        .await;
+                    }
+                }
+                Ok(meta) if meta.file_type().is_symlink() => {
+                    // Skip symlinks outright: following a symlinked directory could
+                    // reintroduce a cycle, and symlinked files are rare enough for addon
+                    // packs that it's not worth special-casing them here.
+                }
+                Ok(_) => {
+                    if detect_format_by_content
+                        || archive_file_extension_list.from_path(&path).is_some()
+                    {
+                        out.push(path);
                     }
                 }
                 Err(e) => {
-                    log::warn!("cannot read \"{}\": {}", path.display(), e);
+                    log::warn!("cannot stat \"{}\": {}", path.display(), e);
                 }
             }
         }
-    }
+    })
 }
 
 #[cfg(target_arch = "wasm32")]
-async fn load_from_addon_dirs(_: &mut Archive, dirs: &[PathBuf], _: &ArchiveFileExtensionList) {
+async fn load_from_addon_dirs(
+    _: &mut Archive,
+    dirs: &[PathBuf],
+    _: &ArchiveFileExtensionList,
+    _: bool,
+    _: bool,
+) {
     assert!(dirs.is_empty(), "addon not supported");
 }